@@ -0,0 +1,471 @@
+//! A text assembler for classic BPF programs.
+//!
+//! This parses the tcpdump-style mnemonics used throughout the kernel's BPF
+//! filter documentation (and emitted by tools like `tcpdump -dd`), so filters
+//! can be written as readable assembly instead of hand-encoded `Op` tuples.
+
+use crate::opcodes::{
+    BPF_ABS, BPF_ALU, BPF_B, BPF_H, BPF_IMM, BPF_IND, BPF_JA, BPF_JEQ, BPF_JGE, BPF_JGT, BPF_JMP,
+    BPF_JSET, BPF_LD, BPF_LDX, BPF_LEN, BPF_MEM, BPF_MISC, BPF_MSH, BPF_RET, BPF_RET_A, BPF_ST,
+    BPF_STX, BPF_TAX, BPF_TXA, BPF_W, BPF_X,
+};
+use crate::{Op, Prog};
+use std::fmt;
+
+/// An error produced while parsing BPF assembly source.
+///
+/// Carries the 1-based source line number of the offending instruction so
+/// callers can point users at the exact spot that failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// The 1-based line number where the error occurred, or `0` when the
+    /// error isn't tied to a line (an empty program).
+    pub line: usize,
+    /// A human-readable description of the problem.
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// The maximum distance, in instructions, a conditional jump may travel: the
+/// 8-bit `jt`/`jf` offsets can't encode anything farther.
+const MAX_COND_JUMP: usize = 255;
+
+enum Addr {
+    Imm(u32),
+    Len,
+    Mem(u32),
+    Abs(u32),
+    Ind(u32),
+    Msh(u32),
+}
+
+fn parse_num(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
+fn parse_addr(operand: &str) -> Option<Addr> {
+    let operand = operand.trim();
+    if let Some(k) = operand.strip_prefix('#') {
+        let k = k.trim();
+        if k.eq_ignore_ascii_case("len") {
+            return Some(Addr::Len);
+        }
+        return parse_num(k).map(Addr::Imm);
+    }
+    if let Some(inner) = operand.strip_prefix("M[").and_then(|s| s.strip_suffix(']')) {
+        return parse_num(inner).map(Addr::Mem);
+    }
+    if let Some(inner) = operand.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let inner = inner.trim();
+        if let Some(rest) = inner.strip_prefix('x') {
+            let rest = rest.trim().strip_prefix('+')?.trim();
+            return parse_num(rest).map(Addr::Ind);
+        }
+        return parse_num(inner).map(Addr::Abs);
+    }
+    if let Some(inner) = operand
+        .strip_prefix("4*([")
+        .and_then(|s| s.strip_suffix("]&0xf)"))
+    {
+        return parse_num(inner).map(Addr::Msh);
+    }
+    None
+}
+
+/// Either an immediate value (`#k`) or the index register (`x`), as used by
+/// ALU and JMP operands.
+enum Scalar {
+    Imm(u32),
+    X,
+}
+
+fn parse_scalar(operand: &str) -> Option<Scalar> {
+    let operand = operand.trim();
+    if operand.eq_ignore_ascii_case("x") {
+        return Some(Scalar::X);
+    }
+    let k = operand.strip_prefix('#').unwrap_or(operand);
+    parse_num(k).map(Scalar::Imm)
+}
+
+struct RawJump<'a> {
+    op: u16,
+    negate: bool,
+    operand: Scalar,
+    jt: Option<&'a str>,
+    jf: Option<&'a str>,
+}
+
+fn split_operands(rest: &str) -> Vec<&str> {
+    rest.split(',').map(str::trim).filter(|s| !s.is_empty()).collect()
+}
+
+/// Parses tcpdump-style BPF assembly source into a [`Prog`].
+///
+/// One instruction per line. A label that the following instruction can be
+/// jumped to by name is defined either on its own line (`accept:`) or
+/// prefixing an instruction on the same line (`accept: ret #0x40000`).
+/// Comments start with `;` and run to the end of the line. Conditional jumps
+/// (`jeq`, `jgt`, `jge`, `jset`, and their negated/derived forms
+/// `jneq`/`jne`/`jlt`/`jle`) take one or two label operands for the true/false
+/// branch; `jmp`/`ja` take exactly one.
+///
+/// # Errors
+///
+/// Returns an [`AsmError`] naming the offending line if a mnemonic or
+/// operand can't be parsed, a jump target label is undefined, or a
+/// conditional jump's target is farther than 255 instructions away.
+///
+/// # Examples
+///
+/// ```
+/// use bpf::Prog;
+///
+/// let prog = Prog::parse(
+///     "ldh [12]\n\
+///      jeq #0x800, accept, drop\n\
+///      drop: ret #0\n\
+///      accept: ret #0x40000\n",
+/// )
+/// .unwrap();
+/// ```
+pub fn parse(src: &str) -> Result<Prog, AsmError> {
+    // First pass: strip comments/blank lines, record label positions against
+    // the index of the instruction they precede.
+    struct Line<'a> {
+        number: usize,
+        text: &'a str,
+    }
+    let mut labels = std::collections::HashMap::new();
+    let mut lines = Vec::new();
+    let mut pc = 0usize;
+    for (idx, raw) in src.lines().enumerate() {
+        let number = idx + 1;
+        let mut text = match raw.find(';') {
+            Some(pos) => &raw[..pos],
+            None => raw,
+        }
+        .trim();
+        if text.is_empty() {
+            continue;
+        }
+        // A label is either the whole line (`accept:`) or a plain-identifier
+        // prefix of it (`accept: ret #0x40000`); anything else containing a
+        // `:` (there isn't any in this grammar) falls through untouched.
+        if let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            let is_label = !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '_');
+            if is_label {
+                if labels.insert(label.to_string(), pc).is_some() {
+                    return Err(AsmError::new(number, format!("duplicate label `{label}`")));
+                }
+                text = text[colon + 1..].trim();
+                if text.is_empty() {
+                    continue;
+                }
+            }
+        }
+        lines.push(Line { number, text });
+        pc += 1;
+    }
+    if lines.is_empty() {
+        return Err(AsmError::new(0, "empty program"));
+    }
+
+    let resolve = |from_pc: usize, name: &str, number: usize| -> Result<usize, AsmError> {
+        labels
+            .get(name)
+            .copied()
+            .ok_or_else(|| AsmError::new(number, format!("undefined label `{name}`")))
+            .and_then(|target| {
+                if target < from_pc + 1 {
+                    return Err(AsmError::new(
+                        number,
+                        format!("label `{name}` does not point forward of the jump"),
+                    ));
+                }
+                Ok(target - (from_pc + 1))
+            })
+    };
+
+    let mut ops = Vec::with_capacity(lines.len());
+    for (pc, line) in lines.iter().enumerate() {
+        let number = line.number;
+        let (mnemonic, rest) = match line.text.split_once(char::is_whitespace) {
+            Some((m, r)) => (m, r.trim()),
+            None => (line.text, ""),
+        };
+
+        let op = match mnemonic {
+            "ld" | "ldh" | "ldb" | "ldx" => {
+                let size = match mnemonic {
+                    "ldh" => BPF_H,
+                    "ldb" => BPF_B,
+                    _ => BPF_W,
+                };
+                let class = if mnemonic == "ldx" { BPF_LDX } else { BPF_LD };
+                let addr = parse_addr(rest)
+                    .ok_or_else(|| AsmError::new(number, format!("invalid operand `{rest}`")))?;
+                let (mode, k) = match addr {
+                    Addr::Imm(k) => (BPF_IMM, k),
+                    Addr::Len => (BPF_LEN, 0),
+                    Addr::Mem(k) => (BPF_MEM, k),
+                    Addr::Abs(k) => (BPF_ABS, k),
+                    Addr::Ind(k) => (BPF_IND, k),
+                    Addr::Msh(k) => (BPF_MSH, k),
+                };
+                // BPF_MSH (the IP-header-length idiom) is only ever valid at
+                // byte size, regardless of which load mnemonic named it; the
+                // kernel and our own validator reject anything else.
+                let size = if mode == BPF_MSH { BPF_B } else { size };
+                Op::new(class | size | mode, 0, 0, k)
+            }
+            "st" | "stx" => {
+                let inner = rest
+                    .strip_prefix("M[")
+                    .and_then(|s| s.strip_suffix(']'))
+                    .ok_or_else(|| AsmError::new(number, format!("invalid operand `{rest}`")))?;
+                let k = parse_num(inner)
+                    .ok_or_else(|| AsmError::new(number, format!("invalid operand `{rest}`")))?;
+                let class = if mnemonic == "st" { BPF_ST } else { BPF_STX };
+                Op::new(class, 0, 0, k)
+            }
+            "jmp" | "ja" => {
+                let target = rest.trim();
+                let k = resolve(pc, target, number)?;
+                Op::new(BPF_JMP | BPF_JA, 0, 0, k as u32)
+            }
+            "jeq" | "jneq" | "jne" | "jlt" | "jle" | "jgt" | "jge" | "jset" => {
+                let operands = split_operands(rest);
+                let (operand_str, jt_label, jf_label) = match operands.as_slice() {
+                    [operand, jt] => (*operand, Some(*jt), None),
+                    [operand, jt, jf] => (*operand, Some(*jt), Some(*jf)),
+                    _ => {
+                        return Err(AsmError::new(
+                            number,
+                            format!("`{mnemonic}` expects an operand and one or two labels"),
+                        ))
+                    }
+                };
+                let operand = parse_scalar(operand_str)
+                    .ok_or_else(|| AsmError::new(number, format!("invalid operand `{operand_str}`")))?;
+                let raw = match mnemonic {
+                    "jeq" => RawJump { op: BPF_JEQ, negate: false, operand, jt: jt_label, jf: jf_label },
+                    "jneq" | "jne" => RawJump { op: BPF_JEQ, negate: true, operand, jt: jt_label, jf: jf_label },
+                    "jlt" => RawJump { op: BPF_JGE, negate: true, operand, jt: jt_label, jf: jf_label },
+                    "jle" => RawJump { op: BPF_JGT, negate: true, operand, jt: jt_label, jf: jf_label },
+                    "jgt" => RawJump { op: BPF_JGT, negate: false, operand, jt: jt_label, jf: jf_label },
+                    "jge" => RawJump { op: BPF_JGE, negate: false, operand, jt: jt_label, jf: jf_label },
+                    "jset" => RawJump { op: BPF_JSET, negate: false, operand, jt: jt_label, jf: jf_label },
+                    _ => unreachable!(),
+                };
+                let (src, k) = match raw.operand {
+                    Scalar::Imm(k) => (0, k),
+                    Scalar::X => (BPF_X, 0),
+                };
+                // `mnemonic target` (one label) means "jump to target on
+                // true, fall through on false"; negated forms swap that.
+                let (true_label, false_label) = match (raw.jt, raw.jf) {
+                    (Some(jt), Some(jf)) => (Some(jt), Some(jf)),
+                    (Some(only), None) => (Some(only), None),
+                    (None, _) => unreachable!("validated above"),
+                };
+                let (jt_name, jf_name) = if raw.negate {
+                    (false_label, true_label)
+                } else {
+                    (true_label, false_label)
+                };
+                let jt = match jt_name {
+                    Some(name) => resolve(pc, name, number)?,
+                    None => 0,
+                };
+                let jf = match jf_name {
+                    Some(name) => resolve(pc, name, number)?,
+                    None => 0,
+                };
+                if jt > MAX_COND_JUMP || jf > MAX_COND_JUMP {
+                    return Err(AsmError::new(
+                        number,
+                        "conditional jump target more than 255 instructions away",
+                    ));
+                }
+                Op::new(BPF_JMP | raw.op | src, jt as u8, jf as u8, k)
+            }
+            "add" | "sub" | "mul" | "div" | "mod" | "and" | "or" | "xor" | "lsh" | "rsh" => {
+                let operand = parse_scalar(rest)
+                    .ok_or_else(|| AsmError::new(number, format!("invalid operand `{rest}`")))?;
+                let alu_op: u16 = match mnemonic {
+                    "add" => 0x00,
+                    "sub" => 0x10,
+                    "mul" => 0x20,
+                    "div" => 0x30,
+                    "or" => 0x40,
+                    "and" => 0x50,
+                    "lsh" => 0x60,
+                    "rsh" => 0x70,
+                    "mod" => 0x90,
+                    "xor" => 0xa0,
+                    _ => unreachable!(),
+                };
+                let (src, k) = match operand {
+                    Scalar::Imm(k) => (0, k),
+                    Scalar::X => (BPF_X, 0),
+                };
+                Op::new(BPF_ALU | alu_op | src, 0, 0, k)
+            }
+            "neg" => Op::new(BPF_ALU | 0x80, 0, 0, 0),
+            "tax" => Op::new(BPF_MISC | BPF_TAX, 0, 0, 0),
+            "txa" => Op::new(BPF_MISC | BPF_TXA, 0, 0, 0),
+            "ret" => {
+                if rest.eq_ignore_ascii_case("a") {
+                    Op::new(BPF_RET | BPF_RET_A, 0, 0, 0)
+                } else {
+                    let k = parse_scalar(rest)
+                        .and_then(|s| match s {
+                            Scalar::Imm(k) => Some(k),
+                            Scalar::X => None,
+                        })
+                        .ok_or_else(|| AsmError::new(number, format!("invalid operand `{rest}`")))?;
+                    Op::new(BPF_RET, 0, 0, k)
+                }
+            }
+            other => return Err(AsmError::new(number, format!("unknown mnemonic `{other}`"))),
+        };
+        ops.push(op);
+    }
+
+    Ok(Prog::new(ops))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::run;
+
+    #[test]
+    fn parses_a_whole_line_label() {
+        let prog = parse(
+            "ldh [12]\n\
+             jeq #0x800, accept, drop\n\
+             drop:\n\
+             ret #0\n\
+             accept:\n\
+             ret #0x40000\n",
+        )
+        .unwrap();
+        let mut ip_packet = vec![0u8; 12];
+        ip_packet.extend_from_slice(&[0x08, 0x00]);
+        assert_eq!(run(&prog, &ip_packet), 0x40000);
+    }
+
+    #[test]
+    fn parses_an_inline_label() {
+        let prog = parse(
+            "ldh [12]\n\
+             jeq #0x800, accept, drop\n\
+             drop: ret #0\n\
+             accept: ret #0x40000\n",
+        )
+        .unwrap();
+        let mut ip_packet = vec![0u8; 12];
+        ip_packet.extend_from_slice(&[0x08, 0x00]);
+        assert_eq!(run(&prog, &ip_packet), 0x40000);
+        let mut other_packet = vec![0u8; 12];
+        other_packet.extend_from_slice(&[0x08, 0x06]);
+        assert_eq!(run(&prog, &other_packet), 0);
+    }
+
+    #[test]
+    fn empty_program_is_rejected() {
+        assert!(parse("").is_err());
+        assert!(parse("; just a comment\n").is_err());
+    }
+
+    #[test]
+    fn unknown_mnemonic_is_rejected() {
+        let err = parse("frob #1\n").unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+
+    #[test]
+    fn undefined_label_is_rejected() {
+        let err = parse("jmp nowhere\n").unwrap_err();
+        assert!(err.message.contains("undefined label"));
+    }
+
+    #[test]
+    fn duplicate_label_is_rejected() {
+        let err = parse("a: ret #0\na: ret #1\n").unwrap_err();
+        assert!(err.message.contains("duplicate label"));
+    }
+
+    #[test]
+    fn backward_jump_is_rejected() {
+        let err = parse("again: ret #0\njmp again\n").unwrap_err();
+        assert!(err.message.contains("forward"));
+    }
+
+    #[test]
+    fn conditional_jump_too_far_is_rejected() {
+        let mut src = String::from("jeq #1, accept\n");
+        for _ in 0..300 {
+            src.push_str("ret #0\n");
+        }
+        src.push_str("accept: ret #1\n");
+        let err = parse(&src).unwrap_err();
+        assert!(err.message.contains("255"));
+    }
+
+    #[test]
+    fn jset_matches_overlapping_bits() {
+        let prog = parse("ld #0x06\njset #0x02, yes, no\nno: ret #0\nyes: ret #1\n").unwrap();
+        assert_eq!(run(&prog, b""), 1);
+    }
+
+    #[test]
+    fn jne_is_the_negation_of_jeq() {
+        let prog = parse("ld #5\njne #5, miss, hit\nmiss: ret #0\nhit: ret #1\n").unwrap();
+        assert_eq!(run(&prog, b""), 1);
+    }
+
+    #[test]
+    fn single_label_jeq_falls_through_on_mismatch() {
+        let prog = parse("ld #1\njeq #2, accept\nret #9\naccept: ret #1\n").unwrap();
+        assert_eq!(run(&prog, b""), 9);
+    }
+
+    #[test]
+    fn alu_and_ret_a_round_trip() {
+        let prog = parse("ld #2\nadd #3\nret a\n").unwrap();
+        assert_eq!(run(&prog, b""), 5);
+    }
+
+    #[test]
+    fn ldx_msh_assembles_at_byte_size_regardless_of_mnemonic() {
+        let prog = parse("ldx 4*([0]&0xf)\ntxa\nret a\n").unwrap();
+        assert_eq!(prog.ops()[0].code, 0xb1);
+        assert!(prog.validate().is_ok());
+        assert_eq!(run(&prog, &[0x45]), 20);
+    }
+}
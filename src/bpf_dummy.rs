@@ -3,10 +3,55 @@ use std::os::unix::io::RawFd;
 
 /// A dummy implementation of BPF program for non-Linux systems.
 ///
-/// On systems that don't support BPF filters, this provides API compatibility
-/// without any actual filtering functionality.
+/// On systems that don't support BPF filters, this doesn't attach to any
+/// socket, but it keeps hold of its instructions so that [`Prog::run`] can
+/// still interpret the program in userspace.
 #[derive(Debug, Clone, Default)]
-pub struct Prog;
+pub struct Prog {
+    ops: Vec<Op>,
+}
+
+impl Prog {
+    /// Creates a new BPF program from a vector of operations.
+    ///
+    /// On non-Linux systems this doesn't attach to anything, but the
+    /// instructions are kept so the program can still be run in userspace
+    /// via [`Prog::run`].
+    ///
+    /// # Parameters
+    ///
+    /// * `ops` - A vector of BPF operations that make up the program
+    pub fn new(ops: Vec<Op>) -> Self {
+        Self { ops }
+    }
+
+    /// Returns the program's instructions as a slice.
+    pub(crate) fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Runs this program against a raw packet in userspace, exactly as the
+    /// kernel's `SK_RUN_FILTER` would, returning the accept length.
+    ///
+    /// See [`crate::run`] for details of the interpreter.
+    pub fn run(&self, packet: &[u8]) -> u32 {
+        crate::interp::run(self, packet)
+    }
+
+    /// Parses tcpdump-style BPF assembly source into a program.
+    ///
+    /// See [`crate::asm::parse`] for the supported syntax.
+    pub fn parse(src: &str) -> Result<Self, crate::AsmError> {
+        crate::asm::parse(src)
+    }
+
+    /// Validates this program against the kernel's classic-BPF sanity checks.
+    ///
+    /// See [`crate::validate::validate`] for exactly what's checked.
+    pub fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::validate::validate(self)
+    }
+}
 
 /// A dummy implementation of BPF operation for non-Linux systems.
 ///
@@ -41,19 +86,28 @@ impl Op {
     }
 }
 
+crate::opcodes::impl_op_builder!(Op);
+
 /// Macro for creating dummy BPF programs on non-Linux systems.
 ///
-/// This macro provides API compatibility with the Linux version, but creates
-/// a dummy program that doesn't perform any actual filtering on non-Linux systems.
+/// This macro provides API compatibility with the Linux version. The program
+/// isn't attached to any real socket on non-Linux systems, but its
+/// instructions are retained so it can still be run in userspace via
+/// [`Prog::run`].
 ///
 /// # Parameters
 ///
-/// * `$count` - The number of operations in the program (ignored)
-/// * `$code $jt $jf $k` - Repeated tuples of operation parameters (ignored)
+/// * `$count` - The number of operations in the program (for capacity pre-allocation)
+/// * `$code $jt $jf $k` - Repeated tuples of operation code, jump-true offset,
+///   jump-false offset, and k-value for each operation
 #[macro_export]
 macro_rules! bpfprog {
     ($count:expr, $($code:tt $jt:tt $jf:tt $k:tt),*) => {
-        $crate::Prog::default()
+        {
+            let mut ops = Vec::with_capacity($count);
+            $(ops.push($crate::Op::new($code, $jt, $jf, $k));)*
+            $crate::Prog::new(ops)
+        }
     };
 }
 
@@ -108,3 +162,74 @@ pub fn detach_filter(fd: RawFd) -> Result<(), Error> {
 pub fn lock_filter(fd: RawFd) -> Result<(), Error> {
     Ok(())
 }
+
+/// Attaches an already-loaded eBPF program to a socket (dummy implementation).
+///
+/// On non-Linux systems, this function does nothing and always returns success.
+/// It provides API compatibility with the Linux version.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket (ignored)
+/// * `prog_fd` - Raw file descriptor of the loaded eBPF program (ignored)
+///
+/// # Returns
+///
+/// Always returns `Ok(())` on non-Linux systems.
+#[allow(unused_variables)]
+pub fn attach_ebpf(fd: RawFd, prog_fd: RawFd) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Detaches any eBPF program from a socket (dummy implementation).
+///
+/// On non-Linux systems, this function does nothing and always returns success.
+/// It provides API compatibility with the Linux version.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket (ignored)
+///
+/// # Returns
+///
+/// Always returns `Ok(())` on non-Linux systems.
+#[allow(unused_variables)]
+pub fn detach_ebpf(fd: RawFd) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Attaches a `SO_REUSEPORT` socket-selection filter (dummy implementation).
+///
+/// On non-Linux systems, this function does nothing and always returns success.
+/// It provides API compatibility with the Linux version.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket (ignored)
+/// * `prog` - The BPF program used to steer packets across the group (ignored)
+///
+/// # Returns
+///
+/// Always returns `Ok(())` on non-Linux systems.
+#[allow(unused_variables)]
+pub fn attach_reuseport_filter(fd: RawFd, prog: Prog) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Attaches an eBPF `SO_REUSEPORT` socket-selection program (dummy implementation).
+///
+/// On non-Linux systems, this function does nothing and always returns success.
+/// It provides API compatibility with the Linux version.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket (ignored)
+/// * `prog_fd` - Raw file descriptor of the loaded eBPF program (ignored)
+///
+/// # Returns
+///
+/// Always returns `Ok(())` on non-Linux systems.
+#[allow(unused_variables)]
+pub fn attach_reuseport_ebpf(fd: RawFd, prog_fd: RawFd) -> Result<(), Error> {
+    Ok(())
+}
@@ -49,6 +49,8 @@ impl Op {
     }
 }
 
+crate::opcodes::impl_op_builder!(Op);
+
 /// Represents a complete BPF program, consisting of a sequence of operations.
 ///
 /// This struct directly maps to the Linux kernel's `sock_fprog` structure.
@@ -106,6 +108,33 @@ impl Prog {
             _ops: Some(ops),
         }
     }
+
+    /// Returns the program's instructions as a slice.
+    pub(crate) fn ops(&self) -> &[Op] {
+        self._ops.as_deref().unwrap_or(&[])
+    }
+
+    /// Runs this program against a raw packet in userspace, exactly as the
+    /// kernel's `SK_RUN_FILTER` would, returning the accept length.
+    ///
+    /// See [`crate::run`] for details of the interpreter.
+    pub fn run(&self, packet: &[u8]) -> u32 {
+        crate::interp::run(self, packet)
+    }
+
+    /// Parses tcpdump-style BPF assembly source into a program.
+    ///
+    /// See [`crate::asm::parse`] for the supported syntax.
+    pub fn parse(src: &str) -> Result<Self, crate::AsmError> {
+        crate::asm::parse(src)
+    }
+
+    /// Validates this program against the kernel's classic-BPF sanity checks.
+    ///
+    /// See [`crate::validate::validate`] for exactly what's checked.
+    pub fn validate(&self) -> Result<(), crate::ValidationError> {
+        crate::validate::validate(self)
+    }
 }
 
 // No longer need custom Drop impl as we're using proper Rust ownership
@@ -113,6 +142,10 @@ impl Prog {
 const SO_ATTACH_FILTER: c_int = 26;
 const SO_DETACH_FILTER: c_int = 27;
 const SO_LOCK_FILTER: c_int = 44;
+const SO_ATTACH_BPF: c_int = 50;
+const SO_DETACH_BPF: c_int = SO_DETACH_FILTER;
+const SO_ATTACH_REUSEPORT_CBPF: c_int = 51;
+const SO_ATTACH_REUSEPORT_EBPF: c_int = 52;
 
 /// Macro for creating BPF programs with a more concise syntax.
 ///
@@ -185,6 +218,13 @@ macro_rules! bpfprog {
 /// This function is safe to call, but internally uses unsafe code to interact
 /// with the operating system. The `fd` must refer to a valid socket.
 pub fn attach_filter(fd: RawFd, prog: Prog) -> Result<(), Error> {
+    // Enable the `validate-on-attach` feature to reject malformed programs
+    // with a descriptive `ValidationError` instead of a bare `EINVAL`.
+    #[cfg(feature = "validate-on-attach")]
+    if let Err(err) = prog.validate() {
+        return Err(Error::new(std::io::ErrorKind::InvalidInput, err.to_string()));
+    }
+
     let ret = unsafe {
         setsockopt(
             fd as c_int,
@@ -302,3 +342,145 @@ pub fn lock_filter(fd: RawFd) -> Result<(), Error> {
         Err(Error::last_os_error())
     }
 }
+
+/// Attaches an already-loaded eBPF program to a socket.
+///
+/// Unlike [`attach_filter`], which ships a classic BPF program down to the
+/// kernel, this attaches a `BPF_PROG_TYPE_SOCKET_FILTER` program that has
+/// already been loaded (e.g. via `bpf(BPF_PROG_LOAD, ...)`) elsewhere,
+/// identified by its program file descriptor.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket
+/// * `prog_fd` - Raw file descriptor of the loaded eBPF program
+///
+/// # Returns
+///
+/// * `Ok(())` if the program was successfully attached
+/// * `Err(Error)` with the system error if attachment failed
+///
+/// # Safety
+///
+/// This function is safe to call, but internally uses unsafe code to interact
+/// with the operating system. The `fd` must refer to a valid socket and
+/// `prog_fd` to a valid, already-loaded eBPF program.
+pub fn attach_ebpf(fd: RawFd, prog_fd: RawFd) -> Result<(), Error> {
+    let ret = unsafe {
+        setsockopt(
+            fd as c_int,
+            SOL_SOCKET,
+            SO_ATTACH_BPF,
+            &prog_fd as *const _ as *const c_void,
+            size_of_val(&prog_fd) as socklen_t,
+        )
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+/// Detaches any eBPF program attached via [`attach_ebpf`] from a socket.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket
+///
+/// # Returns
+///
+/// * `Ok(())` if the program was successfully detached
+/// * `Err(Error)` with the system error if detachment failed
+///
+/// # Safety
+///
+/// This function is safe to call, but internally uses unsafe code to interact
+/// with the operating system. The `fd` must refer to a valid socket.
+pub fn detach_ebpf(fd: RawFd) -> Result<(), Error> {
+    let ret = unsafe { setsockopt(fd as c_int, SOL_SOCKET, SO_DETACH_BPF, null(), 0) };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+/// Attaches a classic BPF program that chooses which socket in a
+/// `SO_REUSEPORT` group receives each incoming packet.
+///
+/// This is a distinct capability from [`attach_filter`]: the program's
+/// return value is not treated as an accept length, but as the index of the
+/// socket (within the group) that should receive the packet.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket
+/// * `prog` - The BPF program used to steer packets across the group
+///
+/// # Returns
+///
+/// * `Ok(())` if the filter was successfully attached
+/// * `Err(Error)` with the system error if attachment failed
+///
+/// # Safety
+///
+/// This function is safe to call, but internally uses unsafe code to interact
+/// with the operating system. The `fd` must refer to a valid socket that is
+/// a member of a `SO_REUSEPORT` group.
+pub fn attach_reuseport_filter(fd: RawFd, prog: Prog) -> Result<(), Error> {
+    let ret = unsafe {
+        setsockopt(
+            fd as c_int,
+            SOL_SOCKET,
+            SO_ATTACH_REUSEPORT_CBPF,
+            &prog as *const _ as *const c_void,
+            size_of_val(&prog) as socklen_t,
+        )
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+/// Attaches an already-loaded eBPF program that chooses which socket in a
+/// `SO_REUSEPORT` group receives each incoming packet, by program fd.
+///
+/// # Parameters
+///
+/// * `fd` - Raw file descriptor of the socket
+/// * `prog_fd` - Raw file descriptor of the loaded eBPF program
+///
+/// # Returns
+///
+/// * `Ok(())` if the program was successfully attached
+/// * `Err(Error)` with the system error if attachment failed
+///
+/// # Safety
+///
+/// This function is safe to call, but internally uses unsafe code to interact
+/// with the operating system. The `fd` must refer to a valid socket that is
+/// a member of a `SO_REUSEPORT` group, and `prog_fd` to a valid, already-loaded
+/// eBPF program.
+pub fn attach_reuseport_ebpf(fd: RawFd, prog_fd: RawFd) -> Result<(), Error> {
+    let ret = unsafe {
+        setsockopt(
+            fd as c_int,
+            SOL_SOCKET,
+            SO_ATTACH_REUSEPORT_EBPF,
+            &prog_fd as *const _ as *const c_void,
+            size_of_val(&prog_fd) as socklen_t,
+        )
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
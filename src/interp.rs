@@ -0,0 +1,258 @@
+//! A userspace interpreter for classic BPF programs.
+//!
+//! This reimplements the Linux kernel's `SK_RUN_FILTER` virtual machine so
+//! that filters can be exercised against raw packet bytes without a socket,
+//! e.g. from unit tests.
+
+use crate::opcodes::{
+    BPF_ABS, BPF_ADD, BPF_ALU, BPF_AND, BPF_B, BPF_DIV, BPF_H, BPF_IMM, BPF_IND, BPF_JA, BPF_JEQ,
+    BPF_JGE, BPF_JGT, BPF_JMP, BPF_JSET, BPF_LD, BPF_LDX, BPF_LEN, BPF_LSH, BPF_MEM, BPF_MISC,
+    BPF_MOD, BPF_MSH, BPF_MUL, BPF_NEG, BPF_OR, BPF_RET, BPF_RET_A, BPF_RSH, BPF_SRC_MASK, BPF_ST,
+    BPF_STX, BPF_SUB, BPF_TAX, BPF_TXA, BPF_W, BPF_XOR, SCRATCH_MEM_WORDS,
+};
+use crate::Prog;
+
+/// Reads a big-endian integer of `size` bytes from `packet` at `offset`,
+/// returning `None` if the read would go out of bounds.
+fn load_be(packet: &[u8], offset: usize, size: usize) -> Option<u32> {
+    let end = offset.checked_add(size)?;
+    if end > packet.len() {
+        return None;
+    }
+    Some(
+        packet[offset..end]
+            .iter()
+            .fold(0u32, |acc, &byte| (acc << 8) | u32::from(byte)),
+    )
+}
+
+/// Runs a classic BPF program against a raw packet in userspace, exactly as
+/// the kernel's `SK_RUN_FILTER` would, returning the accept length (`0`
+/// means drop the packet).
+///
+/// # Parameters
+///
+/// * `prog` - The BPF program to execute
+/// * `packet` - The raw packet bytes to filter
+///
+/// # Examples
+///
+/// ```
+/// use bpf::{bpfprog, run};
+///
+/// // ret #1 (accept every packet, 1 byte)
+/// let filter = bpfprog!(1, 0x06 0 0 0x00000001);
+/// assert_eq!(run(&filter, b"hello"), 1);
+/// ```
+pub fn run(prog: &Prog, packet: &[u8]) -> u32 {
+    let ops = prog.ops();
+    let mut a: u32 = 0;
+    let mut x: u32 = 0;
+    let mut mem = [0u32; SCRATCH_MEM_WORDS];
+    let mut pc: usize = 0;
+
+    loop {
+        let op = match ops.get(pc) {
+            Some(op) => op,
+            None => return 0,
+        };
+        let class = op.code & 0x07;
+
+        match class {
+            BPF_LD | BPF_LDX => {
+                let size = op.code & 0x18;
+                let mode = op.code & 0xe0;
+                let value = match mode {
+                    BPF_IMM => op.k,
+                    BPF_LEN => packet.len() as u32,
+                    BPF_MEM => {
+                        let idx = op.k as usize;
+                        if idx >= SCRATCH_MEM_WORDS {
+                            return 0;
+                        }
+                        mem[idx]
+                    }
+                    BPF_ABS | BPF_IND => {
+                        let base = op.k as usize + if mode == BPF_IND { x as usize } else { 0 };
+                        let width = match size {
+                            BPF_W => 4,
+                            BPF_H => 2,
+                            BPF_B => 1,
+                            _ => return 0,
+                        };
+                        match load_be(packet, base, width) {
+                            Some(value) => value,
+                            None => return 0,
+                        }
+                    }
+                    BPF_MSH => match packet.get(op.k as usize) {
+                        Some(&byte) => u32::from(byte & 0x0f) * 4,
+                        None => return 0,
+                    },
+                    _ => return 0,
+                };
+                if class == BPF_LD {
+                    a = value;
+                } else {
+                    x = value;
+                }
+                pc += 1;
+            }
+            BPF_ST => {
+                let idx = op.k as usize;
+                if idx >= SCRATCH_MEM_WORDS {
+                    return 0;
+                }
+                mem[idx] = a;
+                pc += 1;
+            }
+            BPF_STX => {
+                let idx = op.k as usize;
+                if idx >= SCRATCH_MEM_WORDS {
+                    return 0;
+                }
+                mem[idx] = x;
+                pc += 1;
+            }
+            BPF_ALU => {
+                let operand = if op.code & BPF_SRC_MASK != 0 { x } else { op.k };
+                match op.code & 0xf0 {
+                    BPF_ADD => a = a.wrapping_add(operand),
+                    BPF_SUB => a = a.wrapping_sub(operand),
+                    BPF_MUL => a = a.wrapping_mul(operand),
+                    BPF_DIV => {
+                        if operand == 0 {
+                            return 0;
+                        }
+                        a /= operand;
+                    }
+                    BPF_OR => a |= operand,
+                    BPF_AND => a &= operand,
+                    BPF_LSH => a = a.wrapping_shl(operand),
+                    BPF_RSH => a = a.wrapping_shr(operand),
+                    BPF_NEG => a = a.wrapping_neg(),
+                    BPF_MOD => {
+                        if operand == 0 {
+                            return 0;
+                        }
+                        a %= operand;
+                    }
+                    BPF_XOR => a ^= operand,
+                    _ => return 0,
+                }
+                pc += 1;
+            }
+            BPF_JMP => {
+                if op.code & 0xf0 == BPF_JA {
+                    pc = pc + 1 + op.k as usize;
+                    continue;
+                }
+                let operand = if op.code & BPF_SRC_MASK != 0 { x } else { op.k };
+                let taken = match op.code & 0xf0 {
+                    BPF_JEQ => a == operand,
+                    BPF_JGT => a > operand,
+                    BPF_JGE => a >= operand,
+                    BPF_JSET => a & operand != 0,
+                    _ => return 0,
+                };
+                pc = pc + 1 + usize::from(if taken { op.jt } else { op.jf });
+            }
+            BPF_RET => {
+                return if op.code & 0x18 == BPF_RET_A { a } else { op.k };
+            }
+            BPF_MISC => {
+                match op.code & 0xf8 {
+                    BPF_TAX => x = a,
+                    BPF_TXA => a = x,
+                    _ => return 0,
+                }
+                pc += 1;
+            }
+            _ => return 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::{Mode, Op, Prog, Size};
+
+    #[test]
+    fn ret_k_accepts_with_the_given_length() {
+        let prog = Prog::new(vec![Op::ret_k(0xffff_ffff)]);
+        assert_eq!(run(&prog, b"hello"), 0xffff_ffff);
+    }
+
+    #[test]
+    fn ret_a_returns_the_accumulator() {
+        let prog = Prog::new(vec![Op::ld(Size::Word, Mode::Imm, 7), Op::ret_a()]);
+        assert_eq!(run(&prog, b""), 7);
+    }
+
+    #[test]
+    fn abs_load_past_the_end_of_the_packet_drops() {
+        let prog = Prog::new(vec![Op::ld(Size::Word, Mode::Abs, 100), Op::ret_a()]);
+        assert_eq!(run(&prog, b"short"), 0);
+    }
+
+    #[test]
+    fn div_by_zero_drops() {
+        let prog = Prog::new(vec![Op::div(0), Op::ret_a()]);
+        assert_eq!(run(&prog, b""), 0);
+    }
+
+    #[test]
+    fn mod_by_zero_drops() {
+        let prog = Prog::new(vec![Op::rem(0), Op::ret_a()]);
+        assert_eq!(run(&prog, b""), 0);
+    }
+
+    #[test]
+    fn jeq_takes_the_true_branch() {
+        let prog = Prog::new(vec![Op::jeq(0, 0, 1), Op::ret_k(1), Op::ret_k(2)]);
+        assert_eq!(run(&prog, b""), 1);
+    }
+
+    #[test]
+    fn jeq_takes_the_false_branch() {
+        let prog = Prog::new(vec![Op::jeq(5, 0, 1), Op::ret_k(1), Op::ret_k(2)]);
+        assert_eq!(run(&prog, b""), 2);
+    }
+
+    #[test]
+    fn ja_jumps_unconditionally() {
+        let prog = Prog::new(vec![Op::ja(1), Op::ret_k(1), Op::ret_k(2)]);
+        assert_eq!(run(&prog, b""), 2);
+    }
+
+    #[test]
+    fn scratch_memory_round_trips_through_st_and_ld_mem() {
+        let prog = Prog::new(vec![
+            Op::ld(Size::Word, Mode::Imm, 42),
+            Op::st(3),
+            Op::ld(Size::Word, Mode::Imm, 0),
+            Op::ld(Size::Word, Mode::Mem, 3),
+            Op::ret_a(),
+        ]);
+        assert_eq!(run(&prog, b""), 42);
+    }
+
+    #[test]
+    fn msh_derives_x_from_the_ip_header_length_nibble() {
+        let prog = Prog::new(vec![Op::ldx(Size::Byte, Mode::Msh, 0), Op::txa(), Op::ret_a()]);
+        assert_eq!(run(&prog, &[0x45]), 20);
+    }
+
+    #[test]
+    fn len_mode_returns_the_packet_length() {
+        let prog = Prog::new(vec![Op::ld(Size::Word, Mode::Len, 0), Op::ret_a()]);
+        assert_eq!(run(&prog, b"hello"), 5);
+    }
+
+    #[test]
+    fn falling_off_the_end_without_a_ret_drops() {
+        let prog = Prog::new(vec![Op::tax()]);
+        assert_eq!(run(&prog, b""), 0);
+    }
+}
@@ -58,6 +58,55 @@ pub use bpf_dummy::*;
 #[macro_use]
 mod bpf_dummy;
 
+mod opcodes;
+pub use opcodes::{
+    Mode, Size, BPF_ABS, BPF_ADD, BPF_ALU, BPF_AND, BPF_B, BPF_DIV, BPF_H, BPF_IMM, BPF_IND,
+    BPF_JA, BPF_JEQ, BPF_JGE, BPF_JGT, BPF_JMP, BPF_JSET, BPF_K, BPF_LD, BPF_LDX, BPF_LEN,
+    BPF_LSH, BPF_MEM, BPF_MISC, BPF_MOD, BPF_MSH, BPF_MUL, BPF_NEG, BPF_OR, BPF_RET, BPF_RSH,
+    BPF_ST, BPF_STX, BPF_SUB, BPF_W, BPF_X, BPF_XOR,
+};
+
+mod interp;
+pub use interp::run;
+
+mod asm;
+pub use asm::AsmError;
+
+mod validate;
+pub use validate::ValidationError;
+
+#[cfg(target_os = "linux")]
+#[path = "seccomp_linux.rs"]
+pub mod seccomp;
+#[cfg(not(target_os = "linux"))]
+#[path = "seccomp_dummy.rs"]
+pub mod seccomp;
+
+/// Parses tcpdump-style BPF assembly source into a [`Prog`].
+///
+/// This is a macro-friendly wrapper around [`Prog::parse`] for callers who
+/// want the convenience of `bpfprog!`'s infallible style. It panics if `$src`
+/// fails to parse.
+///
+/// # Examples
+///
+/// ```
+/// use bpf::bpfasm;
+///
+/// let filter = bpfasm!(
+///     "ldh [12]\n\
+///      jeq #0x800, accept, drop\n\
+///      drop: ret #0\n\
+///      accept: ret #0x40000\n"
+/// );
+/// ```
+#[macro_export]
+macro_rules! bpfasm {
+    ($src:expr) => {
+        $crate::Prog::parse($src).expect("invalid BPF assembly")
+    };
+}
+
 /// Trait for types that can have BPF filters attached.
 ///
 /// This trait is automatically implemented for any type that implements `AsRawFd`,
@@ -127,6 +176,69 @@ pub trait BpfFilterAttachable: AsRawFd {
     fn lock_filter(&self) -> std::io::Result<()> {
         lock_filter(self.as_raw_fd())
     }
+
+    /// Attaches an already-loaded eBPF program to this object, by program fd.
+    ///
+    /// Unlike [`BpfFilterAttachable::attach_filter`], this binds a
+    /// `BPF_PROG_TYPE_SOCKET_FILTER` program that was loaded elsewhere (e.g.
+    /// via `bpf(BPF_PROG_LOAD, ...)`), giving it access to maps and richer
+    /// packet inspection than a classic BPF program allows.
+    ///
+    /// # Parameters
+    ///
+    /// * `prog_fd` - Raw file descriptor of the loaded eBPF program
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the program was successfully attached
+    /// * `Err(std::io::Error)` with the system error if attachment failed
+    fn attach_ebpf(&self, prog_fd: std::os::unix::io::RawFd) -> std::io::Result<()> {
+        attach_ebpf(self.as_raw_fd(), prog_fd)
+    }
+
+    /// Detaches any eBPF program attached via [`BpfFilterAttachable::attach_ebpf`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the program was successfully detached
+    /// * `Err(std::io::Error)` with the system error if detachment failed
+    fn detach_ebpf(&self) -> std::io::Result<()> {
+        detach_ebpf(self.as_raw_fd())
+    }
+
+    /// Attaches a classic BPF program that chooses which socket in a
+    /// `SO_REUSEPORT` group receives each incoming packet.
+    ///
+    /// This differs from [`BpfFilterAttachable::attach_filter`]: the
+    /// program's return value selects a listener index within the group
+    /// rather than accepting or dropping the packet.
+    ///
+    /// # Parameters
+    ///
+    /// * `prog` - The BPF program used to steer packets across the group
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the filter was successfully attached
+    /// * `Err(std::io::Error)` with the system error if attachment failed
+    fn attach_reuseport_filter(&self, prog: Prog) -> std::io::Result<()> {
+        attach_reuseport_filter(self.as_raw_fd(), prog)
+    }
+
+    /// Attaches an already-loaded eBPF program that chooses which socket in a
+    /// `SO_REUSEPORT` group receives each incoming packet, by program fd.
+    ///
+    /// # Parameters
+    ///
+    /// * `prog_fd` - Raw file descriptor of the loaded eBPF program
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the program was successfully attached
+    /// * `Err(std::io::Error)` with the system error if attachment failed
+    fn attach_reuseport_ebpf(&self, prog_fd: std::os::unix::io::RawFd) -> std::io::Result<()> {
+        attach_reuseport_ebpf(self.as_raw_fd(), prog_fd)
+    }
 }
 
 // Implement the trait for any type that implements AsRawFd
@@ -0,0 +1,459 @@
+//! Named classic-BPF opcode constants and a typed, fluent builder for [`Op`].
+//!
+//! These mirror the kernel's `linux/filter.h`/`linux/bpf_common.h` bit
+//! layout for `sock_filter.code`. They're used internally by the
+//! interpreter, assembler, and pre-attach validator so the three stay in
+//! lock-step, and are re-exported so callers can build instructions by hand
+//! (e.g. `BPF_LD | BPF_H | BPF_ABS`) while remaining fully interoperable with
+//! [`Op::new`].
+//!
+//! [`Op::new`]: crate::Op::new
+
+// Instruction classes (`code & 0x07`).
+/// `BPF_LD` instruction class: load a value into the accumulator (`A`).
+pub const BPF_LD: u16 = 0x00;
+/// `BPF_LDX` instruction class: load a value into the index register (`X`).
+pub const BPF_LDX: u16 = 0x01;
+/// `BPF_ST` instruction class: store `A` into scratch memory.
+pub const BPF_ST: u16 = 0x02;
+/// `BPF_STX` instruction class: store `X` into scratch memory.
+pub const BPF_STX: u16 = 0x03;
+/// `BPF_ALU` instruction class: arithmetic/logic operating on `A`.
+pub const BPF_ALU: u16 = 0x04;
+/// `BPF_JMP` instruction class: conditional or unconditional jump.
+pub const BPF_JMP: u16 = 0x05;
+/// `BPF_RET` instruction class: terminate and return an accept length.
+pub const BPF_RET: u16 = 0x06;
+/// `BPF_MISC` instruction class: miscellaneous register transfers.
+pub const BPF_MISC: u16 = 0x07;
+pub(crate) const BPF_CLASS_MASK: u16 = 0x07;
+
+// Load/store size bits (`code & 0x18`).
+/// 32-bit load/store size.
+pub const BPF_W: u16 = 0x00;
+/// 16-bit load/store size.
+pub const BPF_H: u16 = 0x08;
+/// 8-bit load/store size.
+pub const BPF_B: u16 = 0x10;
+pub(crate) const BPF_SIZE_MASK: u16 = 0x18;
+
+// Load addressing mode bits (`code & 0xe0`).
+/// Addressing mode: `k` is an immediate value.
+pub const BPF_IMM: u16 = 0x00;
+/// Addressing mode: load from packet offset `k`.
+pub const BPF_ABS: u16 = 0x20;
+/// Addressing mode: load from packet offset `X + k`.
+pub const BPF_IND: u16 = 0x40;
+/// Addressing mode: load from scratch memory slot `k`.
+pub const BPF_MEM: u16 = 0x60;
+/// Addressing mode: `k` is ignored, the value is the packet length.
+pub const BPF_LEN: u16 = 0x80;
+/// Addressing mode: `X = 4 * (packet[k] & 0xf)` (IP header length hack).
+pub const BPF_MSH: u16 = 0xa0;
+pub(crate) const BPF_MODE_MASK: u16 = 0xe0;
+
+// ALU operator bits (`code & 0xf0`) and source bit (`code & 0x08`).
+/// `A += src`
+pub const BPF_ADD: u16 = 0x00;
+/// `A -= src`
+pub const BPF_SUB: u16 = 0x10;
+/// `A *= src`
+pub const BPF_MUL: u16 = 0x20;
+/// `A /= src`
+pub const BPF_DIV: u16 = 0x30;
+/// `A |= src`
+pub const BPF_OR: u16 = 0x40;
+/// `A &= src`
+pub const BPF_AND: u16 = 0x50;
+/// `A <<= src`
+pub const BPF_LSH: u16 = 0x60;
+/// `A >>= src`
+pub const BPF_RSH: u16 = 0x70;
+/// `A = -A` (ignores `src`)
+pub const BPF_NEG: u16 = 0x80;
+/// `A %= src`
+pub const BPF_MOD: u16 = 0x90;
+/// `A ^= src`
+pub const BPF_XOR: u16 = 0xa0;
+pub(crate) const BPF_ALU_OP_MASK: u16 = 0xf0;
+
+// JMP operator bits (`code & 0xf0`).
+/// Unconditional jump by `k` instructions.
+pub const BPF_JA: u16 = 0x00;
+/// Jump if `A == src`.
+pub const BPF_JEQ: u16 = 0x10;
+/// Jump if `A > src`.
+pub const BPF_JGT: u16 = 0x20;
+/// Jump if `A >= src`.
+pub const BPF_JGE: u16 = 0x30;
+/// Jump if `A & src != 0`.
+pub const BPF_JSET: u16 = 0x40;
+pub(crate) const BPF_JMP_OP_MASK: u16 = 0xf0;
+
+/// Source bit: use the immediate value `k`.
+pub const BPF_K: u16 = 0x00;
+/// Source bit: use the index register `X`.
+pub const BPF_X: u16 = 0x08;
+pub(crate) const BPF_SRC_MASK: u16 = 0x08;
+
+// RET value-source bits (`code & 0x18`).
+pub(crate) const BPF_RET_K: u16 = 0x00;
+pub(crate) const BPF_RET_A: u16 = 0x10;
+pub(crate) const BPF_RVAL_MASK: u16 = 0x18;
+
+// MISC operator bits (`code & 0xf8`).
+pub(crate) const BPF_TAX: u16 = 0x00;
+pub(crate) const BPF_TXA: u16 = 0x80;
+pub(crate) const BPF_MISCOP_MASK: u16 = 0xf8;
+
+/// Number of 32-bit words in the classic BPF scratch memory (`M[0..16]`).
+pub(crate) const SCRATCH_MEM_WORDS: usize = 16;
+
+/// Maximum number of instructions the kernel accepts in a classic filter.
+pub(crate) const BPF_MAXINSNS: usize = 4096;
+
+/// The size of a packet or scratch-memory load, for use with [`Op::ld`] and
+/// [`Op::ldx`].
+///
+/// [`Op::ld`]: crate::Op::ld
+/// [`Op::ldx`]: crate::Op::ldx
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    /// 32 bits (`BPF_W`).
+    Word,
+    /// 16 bits (`BPF_H`).
+    Half,
+    /// 8 bits (`BPF_B`).
+    Byte,
+}
+
+impl Size {
+    pub(crate) fn bits(self) -> u16 {
+        match self {
+            Size::Word => BPF_W,
+            Size::Half => BPF_H,
+            Size::Byte => BPF_B,
+        }
+    }
+}
+
+/// The addressing mode of a load, for use with [`Op::ld`] and [`Op::ldx`].
+///
+/// [`Op::ld`]: crate::Op::ld
+/// [`Op::ldx`]: crate::Op::ldx
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Load from packet offset `k` (`BPF_ABS`).
+    Abs,
+    /// Load from packet offset `X + k` (`BPF_IND`).
+    Ind,
+    /// `k` is an immediate value (`BPF_IMM`).
+    Imm,
+    /// Load from scratch memory slot `k` (`BPF_MEM`).
+    Mem,
+    /// Load the packet length, ignoring `k` (`BPF_LEN`).
+    Len,
+    /// `X = 4 * (packet[k] & 0xf)` (`BPF_MSH`).
+    Msh,
+}
+
+impl Mode {
+    pub(crate) fn bits(self) -> u16 {
+        match self {
+            Mode::Abs => BPF_ABS,
+            Mode::Ind => BPF_IND,
+            Mode::Imm => BPF_IMM,
+            Mode::Mem => BPF_MEM,
+            Mode::Len => BPF_LEN,
+            Mode::Msh => BPF_MSH,
+        }
+    }
+}
+
+/// Implements fluent builder constructors on `$Op` (the platform's `Op`
+/// type), combining the named constants above into the correct `code`.
+///
+/// Generated once via macro so the Linux and non-Linux `Op` types, which are
+/// distinct structs, stay in lock-step.
+macro_rules! impl_op_builder {
+    ($Op:ty) => {
+        impl $Op {
+            /// Builds a `BPF_LD` instruction: loads into the accumulator `A`.
+            pub fn ld(size: $crate::Size, mode: $crate::Mode, k: u32) -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_LD | size.bits() | mode.bits(),
+                    0,
+                    0,
+                    k,
+                )
+            }
+
+            /// Builds a `BPF_LDX` instruction: loads into the index register `X`.
+            pub fn ldx(size: $crate::Size, mode: $crate::Mode, k: u32) -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_LDX | size.bits() | mode.bits(),
+                    0,
+                    0,
+                    k,
+                )
+            }
+
+            /// Builds a `BPF_ST` instruction: stores `A` into scratch slot `k`.
+            pub fn st(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ST, 0, 0, k)
+            }
+
+            /// Builds a `BPF_STX` instruction: stores `X` into scratch slot `k`.
+            pub fn stx(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_STX, 0, 0, k)
+            }
+
+            /// Builds a `BPF_JMP|BPF_JA` instruction: jumps `k` instructions forward.
+            pub fn ja(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JA, 0, 0, k)
+            }
+
+            /// Builds a `jeq #k, jt, jf` instruction.
+            pub fn jeq(k: u32, jt: u8, jf: u8) -> Self {
+                Self::new($crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JEQ, jt, jf, k)
+            }
+
+            /// Builds a `jeq x, jt, jf` instruction.
+            pub fn jeq_x(jt: u8, jf: u8) -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JEQ | $crate::opcodes::BPF_X,
+                    jt,
+                    jf,
+                    0,
+                )
+            }
+
+            /// Builds a `jgt #k, jt, jf` instruction.
+            pub fn jgt(k: u32, jt: u8, jf: u8) -> Self {
+                Self::new($crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JGT, jt, jf, k)
+            }
+
+            /// Builds a `jgt x, jt, jf` instruction.
+            pub fn jgt_x(jt: u8, jf: u8) -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JGT | $crate::opcodes::BPF_X,
+                    jt,
+                    jf,
+                    0,
+                )
+            }
+
+            /// Builds a `jge #k, jt, jf` instruction.
+            pub fn jge(k: u32, jt: u8, jf: u8) -> Self {
+                Self::new($crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JGE, jt, jf, k)
+            }
+
+            /// Builds a `jge x, jt, jf` instruction.
+            pub fn jge_x(jt: u8, jf: u8) -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JGE | $crate::opcodes::BPF_X,
+                    jt,
+                    jf,
+                    0,
+                )
+            }
+
+            /// Builds a `jset #k, jt, jf` instruction.
+            pub fn jset(k: u32, jt: u8, jf: u8) -> Self {
+                Self::new($crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JSET, jt, jf, k)
+            }
+
+            /// Builds a `jset x, jt, jf` instruction.
+            pub fn jset_x(jt: u8, jf: u8) -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_JMP | $crate::opcodes::BPF_JSET | $crate::opcodes::BPF_X,
+                    jt,
+                    jf,
+                    0,
+                )
+            }
+
+            /// Builds an `add #k` instruction: `A += k`.
+            pub fn add(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_ADD, 0, 0, k)
+            }
+
+            /// Builds an `add x` instruction: `A += X`.
+            pub fn add_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_ADD | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds a `sub #k` instruction: `A -= k`.
+            pub fn sub(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_SUB, 0, 0, k)
+            }
+
+            /// Builds a `sub x` instruction: `A -= X`.
+            pub fn sub_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_SUB | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds a `mul #k` instruction: `A *= k`.
+            pub fn mul(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_MUL, 0, 0, k)
+            }
+
+            /// Builds a `mul x` instruction: `A *= X`.
+            pub fn mul_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_MUL | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds a `div #k` instruction: `A /= k`.
+            pub fn div(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_DIV, 0, 0, k)
+            }
+
+            /// Builds a `div x` instruction: `A /= X`.
+            pub fn div_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_DIV | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds a `rem #k` instruction: `A %= k` (the kernel's `BPF_MOD`).
+            pub fn rem(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_MOD, 0, 0, k)
+            }
+
+            /// Builds a `rem x` instruction: `A %= X` (the kernel's `BPF_MOD`).
+            pub fn rem_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_MOD | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds an `or #k` instruction: `A |= k`.
+            pub fn or(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_OR, 0, 0, k)
+            }
+
+            /// Builds an `or x` instruction: `A |= X`.
+            pub fn or_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_OR | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds an `and #k` instruction: `A &= k`.
+            pub fn and(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_AND, 0, 0, k)
+            }
+
+            /// Builds an `and x` instruction: `A &= X`.
+            pub fn and_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_AND | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds an `lsh #k` instruction: `A <<= k`.
+            pub fn lsh(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_LSH, 0, 0, k)
+            }
+
+            /// Builds an `lsh x` instruction: `A <<= X`.
+            pub fn lsh_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_LSH | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds an `rsh #k` instruction: `A >>= k`.
+            pub fn rsh(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_RSH, 0, 0, k)
+            }
+
+            /// Builds an `rsh x` instruction: `A >>= X`.
+            pub fn rsh_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_RSH | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds an `xor #k` instruction: `A ^= k`.
+            pub fn xor(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_XOR, 0, 0, k)
+            }
+
+            /// Builds an `xor x` instruction: `A ^= X`.
+            pub fn xor_x() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_ALU | $crate::opcodes::BPF_XOR | $crate::opcodes::BPF_X,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds a `neg` instruction: `A = -A`.
+            pub fn neg() -> Self {
+                Self::new($crate::opcodes::BPF_ALU | $crate::opcodes::BPF_NEG, 0, 0, 0)
+            }
+
+            /// Builds a `ret #k` instruction: terminates, returning `k`.
+            pub fn ret_k(k: u32) -> Self {
+                Self::new($crate::opcodes::BPF_RET, 0, 0, k)
+            }
+
+            /// Builds a `ret a` instruction: terminates, returning `A`.
+            pub fn ret_a() -> Self {
+                Self::new(
+                    $crate::opcodes::BPF_RET | $crate::opcodes::BPF_RET_A,
+                    0,
+                    0,
+                    0,
+                )
+            }
+
+            /// Builds a `tax` instruction: `X = A`.
+            pub fn tax() -> Self {
+                Self::new($crate::opcodes::BPF_MISC | $crate::opcodes::BPF_TAX, 0, 0, 0)
+            }
+
+            /// Builds a `txa` instruction: `A = X`.
+            pub fn txa() -> Self {
+                Self::new($crate::opcodes::BPF_MISC | $crate::opcodes::BPF_TXA, 0, 0, 0)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_op_builder;
@@ -0,0 +1,53 @@
+//! Dummy seccomp-BPF implementation for non-Linux systems.
+//!
+//! Mirrors the Linux seccomp module's API so callers can write portable code,
+//! but installing a filter does nothing on non-Linux systems.
+
+use std::io::Error;
+
+use crate::Prog;
+
+/// Byte offset of the `nr` field (syscall number) in `struct seccomp_data`.
+pub const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+/// Byte offset of the `arch` field (audit architecture) in `struct seccomp_data`.
+pub const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+/// Byte offset of the `instruction_pointer` field in `struct seccomp_data`.
+pub const SECCOMP_DATA_INSTRUCTION_POINTER_OFFSET: u32 = 8;
+/// Byte offset of the first syscall argument (`args[0]`) in `struct seccomp_data`.
+pub const SECCOMP_DATA_ARGS_OFFSET: u32 = 16;
+
+/// Returns the byte offset of `args[n]` in `struct seccomp_data`, each
+/// argument being 8 bytes wide.
+pub fn seccomp_data_arg_offset(n: u32) -> u32 {
+    SECCOMP_DATA_ARGS_OFFSET + n * 8
+}
+
+/// Kill the offending thread immediately.
+pub const SECCOMP_RET_KILL: u32 = 0x0000_0000;
+/// Fail the syscall, returning the errno packed into the low 16 bits.
+pub const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+/// Allow the syscall to proceed.
+pub const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+/// Builds a [`SECCOMP_RET_ERRNO`] return value that fails the syscall with
+/// the given `errno`.
+pub fn ret_errno(errno: u16) -> u32 {
+    SECCOMP_RET_ERRNO | u32::from(errno)
+}
+
+/// Installs a seccomp filter (dummy implementation).
+///
+/// On non-Linux systems, this function does nothing and always returns success.
+/// It provides API compatibility with the Linux version.
+///
+/// # Parameters
+///
+/// * `prog` - The BPF program to install as the seccomp filter (ignored)
+///
+/// # Returns
+///
+/// Always returns `Ok(())` on non-Linux systems.
+#[allow(unused_variables)]
+pub fn install(prog: &Prog) -> Result<(), Error> {
+    Ok(())
+}
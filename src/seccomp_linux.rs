@@ -0,0 +1,114 @@
+//! Seccomp-BPF syscall filtering, reusing the same `Op`/`Prog` machinery
+//! this crate uses for socket filters.
+//!
+//! A `Prog` built for a socket filter and one built for seccomp are the same
+//! `sock_filter` program; only the "packet" being inspected differs. Instead
+//! of raw network bytes, a seccomp filter reads the kernel's `seccomp_data`
+//! layout — see the `SECCOMP_DATA_*` offsets below — and returns an action
+//! such as [`SECCOMP_RET_ALLOW`] instead of an accept length.
+
+use libc::{c_int, c_uint, c_ulong, c_void};
+use std::io::Error;
+
+use crate::Prog;
+
+const PR_SET_NO_NEW_PRIVS: c_int = 38;
+const PR_SET_SECCOMP: c_int = 22;
+const SECCOMP_MODE_FILTER: c_ulong = 2;
+const SECCOMP_SET_MODE_FILTER: c_uint = 1;
+
+/// Byte offset of the `nr` field (syscall number) in `struct seccomp_data`.
+pub const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+/// Byte offset of the `arch` field (audit architecture) in `struct seccomp_data`.
+pub const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+/// Byte offset of the `instruction_pointer` field in `struct seccomp_data`.
+pub const SECCOMP_DATA_INSTRUCTION_POINTER_OFFSET: u32 = 8;
+/// Byte offset of the first syscall argument (`args[0]`) in `struct seccomp_data`.
+pub const SECCOMP_DATA_ARGS_OFFSET: u32 = 16;
+
+/// Returns the byte offset of `args[n]` in `struct seccomp_data`, each
+/// argument being 8 bytes wide.
+pub fn seccomp_data_arg_offset(n: u32) -> u32 {
+    SECCOMP_DATA_ARGS_OFFSET + n * 8
+}
+
+// Seccomp filter return-value actions (the upper 16 bits of a `BPF_RET`
+// value; the lower 16 bits carry action-specific data, e.g. an errno for
+// `SECCOMP_RET_ERRNO`).
+/// Kill the offending thread immediately.
+pub const SECCOMP_RET_KILL: u32 = 0x0000_0000;
+/// Fail the syscall, returning the errno packed into the low 16 bits.
+pub const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+/// Allow the syscall to proceed.
+pub const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+/// Builds a [`SECCOMP_RET_ERRNO`] return value that fails the syscall with
+/// the given `errno`.
+pub fn ret_errno(errno: u16) -> u32 {
+    SECCOMP_RET_ERRNO | u32::from(errno)
+}
+
+/// Installs `prog` as the calling thread's seccomp filter.
+///
+/// This first sets `PR_SET_NO_NEW_PRIVS`, which the kernel requires of
+/// unprivileged callers before installing a filter, then installs the
+/// filter via the `seccomp(2)` syscall (`SECCOMP_SET_MODE_FILTER`),
+/// falling back to `prctl(PR_SET_SECCOMP, ...)` on kernels older than 3.17
+/// that don't have `seccomp(2)`.
+///
+/// # Parameters
+///
+/// * `prog` - The BPF program to install as the seccomp filter
+///
+/// # Returns
+///
+/// * `Ok(())` if the filter was successfully installed
+/// * `Err(std::io::Error)` with the system error if installation failed
+///
+/// # Note
+///
+/// A seccomp filter, once installed, cannot be removed for the lifetime of
+/// the thread (and is inherited across `fork`/`execve`); this is irreversible.
+///
+/// # Safety
+///
+/// This function is safe to call, but internally uses unsafe code to interact
+/// with the operating system.
+pub fn install(prog: &Prog) -> Result<(), Error> {
+    let nnp = unsafe { libc::prctl(PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) };
+    if nnp != 0 {
+        return Err(Error::last_os_error());
+    }
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            SECCOMP_SET_MODE_FILTER,
+            0 as c_uint,
+            prog as *const _ as *const c_void,
+        )
+    };
+    if ret == 0 {
+        return Ok(());
+    }
+
+    let err = Error::last_os_error();
+    if err.raw_os_error() != Some(libc::ENOSYS) {
+        return Err(err);
+    }
+
+    let ret = unsafe {
+        libc::prctl(
+            PR_SET_SECCOMP,
+            SECCOMP_MODE_FILTER,
+            prog as *const _ as c_ulong,
+            0,
+            0,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
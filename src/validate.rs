@@ -0,0 +1,334 @@
+//! A pre-attach validator mirroring the kernel's classic-BPF sanity checks.
+//!
+//! `attach_filter` hands a program to the kernel, which rejects anything it
+//! considers malformed with a bare `EINVAL`. This reproduces those checks in
+//! userspace so a bad program can be diagnosed before it ever reaches a
+//! `setsockopt` call.
+
+use crate::opcodes::{
+    BPF_ABS, BPF_ADD, BPF_ALU, BPF_ALU_OP_MASK, BPF_AND, BPF_CLASS_MASK, BPF_DIV, BPF_IMM,
+    BPF_IND, BPF_JA, BPF_JEQ, BPF_JGE, BPF_JGT, BPF_JMP, BPF_JMP_OP_MASK, BPF_JSET, BPF_LD,
+    BPF_LDX, BPF_LEN, BPF_LSH, BPF_MAXINSNS, BPF_MEM, BPF_MISC, BPF_MISCOP_MASK, BPF_MODE_MASK,
+    BPF_MOD, BPF_MSH, BPF_MUL, BPF_NEG, BPF_OR, BPF_RET, BPF_RET_A, BPF_RET_K, BPF_RSH,
+    BPF_RVAL_MASK, BPF_SIZE_MASK, BPF_ST, BPF_STX, BPF_SUB, BPF_TAX, BPF_TXA, BPF_XOR, BPF_B,
+    SCRATCH_MEM_WORDS,
+};
+use crate::Prog;
+use std::fmt;
+
+/// An error produced while validating a classic BPF program.
+///
+/// `index` is the offending instruction's position in the program, or `None`
+/// for whole-program checks (e.g. length limits).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The index of the offending instruction, if the error is specific to one.
+    pub index: Option<usize>,
+    /// A human-readable description of the problem.
+    pub reason: String,
+}
+
+impl ValidationError {
+    fn at(index: usize, reason: impl Into<String>) -> Self {
+        Self {
+            index: Some(index),
+            reason: reason.into(),
+        }
+    }
+
+    fn program(reason: impl Into<String>) -> Self {
+        Self {
+            index: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            Some(index) => write!(f, "instruction {index}: {}", self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Validates a classic BPF program against the same checks the kernel's
+/// verifier applies before accepting a filter.
+///
+/// # Errors
+///
+/// Returns a [`ValidationError`] naming the offending instruction and reason
+/// if:
+/// - the program is empty or longer than `4096` instructions
+/// - any instruction's `code` is not a recognized opcode/mode combination
+/// - a jump target would land outside the program or behind the jump itself
+/// - a scratch-memory access (`ST`, `STX`, or `BPF_MEM`) uses an index `>= 16`
+/// - the final instruction is not a `BPF_RET`
+///
+/// # Examples
+///
+/// ```
+/// use bpf::{bpfprog, Prog};
+///
+/// let prog = bpfprog!(1, 0x06 0 0 0x00000001); // ret #1
+/// assert!(prog.validate().is_ok());
+/// ```
+pub fn validate(prog: &Prog) -> Result<(), ValidationError> {
+    let ops = prog.ops();
+
+    if ops.is_empty() {
+        return Err(ValidationError::program(
+            "program must contain at least one instruction",
+        ));
+    }
+    if ops.len() > BPF_MAXINSNS {
+        return Err(ValidationError::program(format!(
+            "program has {} instructions, exceeding the kernel limit of {BPF_MAXINSNS}",
+            ops.len()
+        )));
+    }
+
+    for (pc, op) in ops.iter().enumerate() {
+        match op.code & BPF_CLASS_MASK {
+            BPF_LD | BPF_LDX => match op.code & BPF_MODE_MASK {
+                BPF_IMM | BPF_LEN => {}
+                BPF_ABS | BPF_IND => {
+                    let size = op.code & BPF_SIZE_MASK;
+                    if size == BPF_SIZE_MASK {
+                        return Err(ValidationError::at(
+                            pc,
+                            format!("unrecognized load size 0x{size:02x}"),
+                        ));
+                    }
+                }
+                BPF_MEM => {
+                    if op.k as usize >= SCRATCH_MEM_WORDS {
+                        return Err(ValidationError::at(
+                            pc,
+                            format!(
+                                "scratch-memory index {} out of range (0..{SCRATCH_MEM_WORDS})",
+                                op.k
+                            ),
+                        ));
+                    }
+                }
+                BPF_MSH => {
+                    let is_ldx_byte =
+                        op.code & BPF_CLASS_MASK == BPF_LDX && op.code & BPF_SIZE_MASK == BPF_B;
+                    if !is_ldx_byte {
+                        return Err(ValidationError::at(
+                            pc,
+                            "BPF_MSH addressing is only valid for BPF_LDX|BPF_B",
+                        ));
+                    }
+                }
+                mode => {
+                    return Err(ValidationError::at(
+                        pc,
+                        format!("unrecognized addressing mode 0x{mode:02x}"),
+                    ))
+                }
+            },
+            BPF_ST | BPF_STX => {
+                if op.k as usize >= SCRATCH_MEM_WORDS {
+                    return Err(ValidationError::at(
+                        pc,
+                        format!(
+                            "scratch-memory index {} out of range (0..{SCRATCH_MEM_WORDS})",
+                            op.k
+                        ),
+                    ));
+                }
+            }
+            BPF_ALU => {
+                let alu_op = op.code & BPF_ALU_OP_MASK;
+                if !matches!(
+                    alu_op,
+                    BPF_ADD | BPF_SUB | BPF_MUL | BPF_DIV | BPF_OR | BPF_AND | BPF_LSH | BPF_RSH
+                        | BPF_NEG | BPF_MOD | BPF_XOR
+                ) {
+                    return Err(ValidationError::at(
+                        pc,
+                        format!("unrecognized ALU operator 0x{alu_op:02x}"),
+                    ));
+                }
+            }
+            BPF_JMP => {
+                let jmp_op = op.code & BPF_JMP_OP_MASK;
+                if jmp_op == BPF_JA {
+                    let target = pc + 1 + op.k as usize;
+                    if target <= pc || target >= ops.len() {
+                        return Err(ValidationError::at(
+                            pc,
+                            format!("jump target {target} out of range"),
+                        ));
+                    }
+                } else if matches!(jmp_op, BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET) {
+                    for offset in [op.jt as usize, op.jf as usize] {
+                        let target = pc + 1 + offset;
+                        if target <= pc || target >= ops.len() {
+                            return Err(ValidationError::at(
+                                pc,
+                                format!("jump target {target} out of range"),
+                            ));
+                        }
+                    }
+                } else {
+                    return Err(ValidationError::at(
+                        pc,
+                        format!("unrecognized jump operator 0x{jmp_op:02x}"),
+                    ));
+                }
+            }
+            BPF_RET => {
+                let rval = op.code & BPF_RVAL_MASK;
+                if !matches!(rval, BPF_RET_K | BPF_RET_A) {
+                    return Err(ValidationError::at(
+                        pc,
+                        format!("unrecognized RET value source 0x{rval:02x}"),
+                    ));
+                }
+            }
+            BPF_MISC => {
+                let miscop = op.code & BPF_MISCOP_MASK;
+                if !matches!(miscop, BPF_TAX | BPF_TXA) {
+                    return Err(ValidationError::at(
+                        pc,
+                        format!("unrecognized MISC operator 0x{miscop:02x}"),
+                    ));
+                }
+            }
+            class => {
+                return Err(ValidationError::at(
+                    pc,
+                    format!("unrecognized instruction class 0x{class:02x}"),
+                ))
+            }
+        }
+    }
+
+    let last = ops.len() - 1;
+    if ops[last].code & BPF_CLASS_MASK != BPF_RET {
+        return Err(ValidationError::at(
+            last,
+            "program does not end in a RET instruction",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+    use crate::opcodes::BPF_MAXINSNS;
+    use crate::{Mode, Op, Prog, Size};
+
+    #[test]
+    fn accepts_a_well_formed_program() {
+        let prog = Prog::new(vec![Op::ld(Size::Word, Mode::Abs, 0), Op::ret_a()]);
+        assert!(validate(&prog).is_ok());
+    }
+
+    #[test]
+    fn rejects_an_empty_program() {
+        let prog = Prog::new(vec![]);
+        assert!(validate(&prog).is_err());
+    }
+
+    #[test]
+    fn rejects_a_program_over_the_instruction_limit() {
+        let mut ops = vec![Op::tax(); BPF_MAXINSNS];
+        ops.push(Op::ret_a());
+        let prog = Prog::new(ops);
+        let err = validate(&prog).unwrap_err();
+        assert!(err.index.is_none());
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_addressing_mode() {
+        // BPF_LD with an invalid mode bit pattern (0xc0, above BPF_MSH).
+        let prog = Prog::new(vec![Op::new(0xc0, 0, 0, 0), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_msh_on_anything_other_than_ldx_byte() {
+        let prog = Prog::new(vec![Op::ld(Size::Word, Mode::Msh, 0), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn accepts_msh_on_ldx_byte() {
+        let prog = Prog::new(vec![Op::ldx(Size::Byte, Mode::Msh, 0), Op::ret_a()]);
+        assert!(validate(&prog).is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_scratch_memory_on_a_load() {
+        let prog = Prog::new(vec![Op::ld(Size::Word, Mode::Mem, 16), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_out_of_range_scratch_memory_on_a_store() {
+        let prog = Prog::new(vec![Op::st(16), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_alu_operator() {
+        let prog = Prog::new(vec![Op::new(0x04 | 0xf0, 0, 0, 0), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_jump_operator() {
+        let prog = Prog::new(vec![Op::new(0x05 | 0xf0, 0, 0, 0), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_a_jump_target_past_the_end_of_the_program() {
+        let prog = Prog::new(vec![Op::ja(5), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_a_conditional_jump_target_past_the_end_of_the_program() {
+        let prog = Prog::new(vec![Op::jeq(0, 5, 0), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_ret_value_source() {
+        let prog = Prog::new(vec![Op::new(0x06 | 0x08, 0, 0, 0)]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_an_unrecognized_misc_operator() {
+        let prog = Prog::new(vec![Op::new(0x07 | 0x40, 0, 0, 0), Op::ret_a()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+
+    #[test]
+    fn rejects_a_program_not_ending_in_ret() {
+        let prog = Prog::new(vec![Op::tax()]);
+        let err = validate(&prog).unwrap_err();
+        assert_eq!(err.index, Some(0));
+    }
+}